@@ -0,0 +1,53 @@
+//! Defines the `Error` type used across this crate, and the `Result` alias
+//! built on top of it.
+
+use std::fmt;
+use std::str::Utf8Error;
+
+use escape::EscapeError;
+
+/// The error type used throughout this crate
+#[derive(Debug)]
+pub enum Error {
+    /// Error while escaping or unescaping a value
+    EscapeError(EscapeError),
+    /// Error while converting bytes to utf8
+    Utf8(Utf8Error),
+    /// Xml declaration without a `version` attribute, or with an unexpected
+    /// attribute in its place. The inner value is the name of the attribute
+    /// found instead of `version`, if any.
+    XmlDeclWithoutVersion(Option<String>),
+    /// Xml declaration whose `version` attribute, once its surrounding
+    /// quotes are stripped, is neither `1.0` nor `1.1`. The inner value is
+    /// the value found, when one could be recovered at all.
+    XmlDeclInvalidVersion(Option<String>),
+    /// Xml declaration whose `standalone` attribute, once its surrounding
+    /// quotes are stripped, is neither `yes` nor `no`. The inner value is
+    /// the value found, when one could be recovered at all.
+    XmlDeclInvalidStandalone(Option<String>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::EscapeError(ref e) => write!(f, "escape error: {:?}", e),
+            Error::Utf8(ref e) => write!(f, "utf8 error: {}", e),
+            Error::XmlDeclWithoutVersion(ref found) => write!(
+                f,
+                "XmlDecl must start with 'version' attribute, found {:?}",
+                found
+            ),
+            Error::XmlDeclInvalidVersion(ref found) => {
+                write!(f, "XmlDecl 'version' must be '1.0' or '1.1', found {:?}", found)
+            }
+            Error::XmlDeclInvalidStandalone(ref found) => write!(
+                f,
+                "XmlDecl 'standalone' must be 'yes' or 'no', found {:?}",
+                found
+            ),
+        }
+    }
+}
+
+/// A specialized `Result` type where the error is always an `Error`
+pub type Result<T> = ::std::result::Result<T, Error>;
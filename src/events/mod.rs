@@ -3,18 +3,78 @@
 pub mod attributes;
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::str::from_utf8;
 use std::ops::Deref;
-use encoding_rs::Encoding;
+use encoding_rs::{Encoding, UTF_8};
 use std::io::BufRead;
 
-use escape::{escape, unescape};
+use escape::{escape, unescape, EscapeError};
 use self::attributes::{Attribute, Attributes};
 use errors::{Error, Result};
 use reader::Reader;
 
 use memchr;
 
+/// Expands entity references in `raw`, the same way `unescape` does, except
+/// that a named reference which isn't one of the five predefined entities or
+/// a numeric character reference is looked up in `custom_entities` instead of
+/// producing an error.
+///
+/// Scans for `&`, reads up to the next `;` (reporting `Malformed` at the
+/// `&`'s index if there is none, same as `unescape` does), and resolves
+/// names in this order: the five predefined entities and numeric character
+/// references (by delegating that single reference to `unescape`, so the
+/// two stay in sync), then `custom_entities`. Stays borrowed when `raw`
+/// contains no `&`.
+fn unescape_with_custom_entities<'a>(
+    raw: &'a [u8],
+    custom_entities: &HashMap<Vec<u8>, Vec<u8>>,
+) -> Result<Cow<'a, [u8]>> {
+    let mut pos = 0;
+    let mut buf: Option<Vec<u8>> = None;
+
+    while let Some(rel) = memchr::memchr(b'&', &raw[pos..]) {
+        let amp = pos + rel;
+        let semi = match memchr::memchr(b';', &raw[amp..]) {
+            Some(i) => amp + i,
+            None => return Err(Error::EscapeError(EscapeError::Malformed(amp))),
+        };
+        let name = &raw[amp + 1..semi];
+        let is_predefined_or_numeric = match name {
+            b"amp" | b"lt" | b"gt" | b"apos" | b"quot" => true,
+            _ => name.first() == Some(&b'#'),
+        };
+
+        let resolved: Vec<u8> = if is_predefined_or_numeric {
+            match unescape(&raw[amp..semi + 1]) {
+                Ok(r) => r.into_owned(),
+                Err(e) => return Err(Error::EscapeError(e)),
+            }
+        } else if let Some(value) = custom_entities.get(name) {
+            value.clone()
+        } else {
+            match unescape(&raw[amp..semi + 1]) {
+                Ok(r) => r.into_owned(),
+                Err(e) => return Err(Error::EscapeError(e)),
+            }
+        };
+
+        let owned = buf.get_or_insert_with(Vec::new);
+        owned.extend_from_slice(&raw[pos..amp]);
+        owned.extend_from_slice(&resolved);
+        pos = semi + 1;
+    }
+
+    Ok(match buf {
+        Some(mut owned) => {
+            owned.extend_from_slice(&raw[pos..]);
+            Cow::Owned(owned)
+        }
+        None => Cow::Borrowed(raw),
+    })
+}
+
 /// A struct to manage `Event::Start` events
 ///
 /// Provides in particular an iterator over attributes
@@ -88,6 +148,19 @@ impl<'a> BytesStart<'a> {
         unescape(&*self.buf).map_err(Error::EscapeError)
     }
 
+    /// gets unescaped content, also resolving custom entities
+    ///
+    /// Like `unescaped()`, but entity references that are not one of the five
+    /// predefined entities or a numeric character reference are looked up in
+    /// `custom_entities` (e.g. entities collected from a document's DTD via
+    /// `BytesDocType::entities_map`) instead of producing an error.
+    pub fn unescaped_with_custom_entities(
+        &self,
+        custom_entities: &HashMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<Cow<[u8]>> {
+        unescape_with_custom_entities(&*self.buf, custom_entities)
+    }
+
     /// gets attributes iterator
     pub fn attributes(&self) -> Attributes {
         Attributes::new(self, self.name_len)
@@ -122,6 +195,17 @@ impl<'a> BytesStart<'a> {
         self.unescaped().map(|e| reader.decode(&*e).into_owned())
     }
 
+    /// helper method to unescape then decode self using the reader encoding,
+    /// additionally resolving custom entities
+    pub fn unescape_and_decode_with_custom_entities<B: BufRead>(
+        &self,
+        reader: &Reader<B>,
+        custom_entities: &HashMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<String> {
+        self.unescaped_with_custom_entities(custom_entities)
+            .map(|e| reader.decode(&*e).into_owned())
+    }
+
     /// Adds an attribute to this element from the given key and value.
     /// Key and value can be anything that implements the AsRef<[u8]> trait,
     /// like byte slices and strings.
@@ -198,6 +282,38 @@ impl<'a> BytesDecl<'a> {
         None
     }
 
+    /// Gets xml version, with the surrounding quotes stripped, validating
+    /// that it is either `1.0` or `1.1`
+    pub fn version_normalized(&self) -> Result<Cow<[u8]>> {
+        let value = strip_quotes(self.version()?).ok_or(Error::XmlDeclInvalidVersion(None))?;
+        match &*value {
+            b"1.0" | b"1.1" => Ok(value),
+            _ => Err(Error::XmlDeclInvalidVersion(Some(
+                String::from_utf8_lossy(&value).into_owned(),
+            ))),
+        }
+    }
+
+    /// Gets xml standalone, with the surrounding quotes stripped, validating
+    /// that it is either `yes` or `no`
+    pub fn standalone_normalized(&self) -> Option<Result<Cow<[u8]>>> {
+        let raw = match self.standalone() {
+            Some(Ok(raw)) => raw,
+            Some(Err(e)) => return Some(Err(e)),
+            None => return None,
+        };
+        let value = match strip_quotes(raw) {
+            Some(value) => value,
+            None => return Some(Err(Error::XmlDeclInvalidStandalone(None))),
+        };
+        Some(match &*value {
+            b"yes" | b"no" => Ok(value),
+            _ => Err(Error::XmlDeclInvalidStandalone(Some(
+                String::from_utf8_lossy(&value).into_owned(),
+            ))),
+        })
+    }
+
     /// Constructs a new `XmlDecl` from the (mandatory) _version_ (should be `1.0` or `1.1`),
     /// the optional _encoding_ (e.g., `UTF-8`) and the optional _standalone_ (`yes` or `no`)
     /// attribute.
@@ -245,11 +361,196 @@ impl<'a> BytesDecl<'a> {
         }
     }
 
-    /// Gets the decoder struct
-    pub fn encoder(&self) -> Option<&'static Encoding> {
+    /// Gets the decoder struct, falling back to UTF-8 when no `encoding`
+    /// attribute is present.
+    ///
+    /// Note that an `encoding` attribute present but not recognized by
+    /// `Encoding::for_label` falls back to the same `UTF_8` result as a
+    /// genuinely absent attribute; this accessor can't tell the two apart.
+    /// Use `encoding()` directly if that distinction matters.
+    pub fn encoder(&self) -> &'static Encoding {
         self.encoding()
             .and_then(|e| e.ok())
+            .and_then(strip_quotes)
             .and_then(|e| Encoding::for_label(&*e))
+            .unwrap_or(UTF_8)
+    }
+}
+
+/// Strips a leading and trailing matching quote character (`'` or `"`) off
+/// `value`, returning `None` if `value` isn't properly quoted.
+fn strip_quotes(value: Cow<[u8]>) -> Option<Cow<[u8]>> {
+    if value.len() < 2 {
+        return None;
+    }
+    let quote = value[0];
+    if (quote != b'\'' && quote != b'"') || value[value.len() - 1] != quote {
+        return None;
+    }
+    Some(match value {
+        Cow::Borrowed(v) => Cow::Borrowed(&v[1..v.len() - 1]),
+        Cow::Owned(mut v) => {
+            v.pop();
+            v.remove(0);
+            Cow::Owned(v)
+        }
+    })
+}
+
+/// Wrapper around `BytesText` to parse the internal subset of a `<!DOCTYPE>`
+///
+/// The raw doctype bytes handed out by `Event::DocType` are opaque to a DOM
+/// builder, which needs the `<!ENTITY>` declarations inside to expand entity
+/// references in the rest of the document. `entities()` walks the internal
+/// subset for those declarations on demand, and `entities_map()` collects
+/// them into the shape `unescaped_with_custom_entities` expects.
+///
+/// [W3C XML 1.1 Document Type Definition](http://w3.org/TR/xml11/#dt-doctype)
+#[derive(Clone, Debug)]
+pub struct BytesDocType<'a> {
+    content: BytesText<'a>,
+}
+
+impl<'a> BytesDocType<'a> {
+    /// Creates a `BytesDocType` from a `BytesText`
+    pub fn from_text(content: BytesText<'a>) -> BytesDocType<'a> {
+        BytesDocType { content: content }
+    }
+
+    /// Returns an iterator over the general internal entity declarations
+    /// (`<!ENTITY name "value">`) found in the internal subset.
+    ///
+    /// Parameter entities (`<!ENTITY % name "value">`) and external or
+    /// unparsed declarations (using `SYSTEM`, `PUBLIC` or `NDATA`) are
+    /// skipped, since they cannot be expanded as plain text the way a
+    /// general internal entity can.
+    pub fn entities(&self) -> BytesDocTypeEntities {
+        BytesDocTypeEntities {
+            content: &*self.content,
+            pos: 0,
+        }
+    }
+
+    /// Collects the general internal entity declarations into a map, ready
+    /// to be fed into `unescaped_with_custom_entities` and friends.
+    ///
+    /// Per the XML spec, the first declaration for a given name is binding;
+    /// a later duplicate declaration for the same name is ignored.
+    pub fn entities_map(&self) -> HashMap<Vec<u8>, Vec<u8>> {
+        let mut entities = HashMap::new();
+        for (name, value) in self.entities() {
+            entities.entry(name).or_insert(value);
+        }
+        entities
+    }
+}
+
+#[inline]
+fn is_xml_whitespace(b: u8) -> bool {
+    b == b' ' || b == b'\t' || b == b'\r' || b == b'\n'
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Iterator over the `<!ENTITY>` declarations of a DOCTYPE internal subset.
+///
+/// Returned by `BytesDocType::entities`.
+#[derive(Clone, Debug)]
+pub struct BytesDocTypeEntities<'a> {
+    content: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BytesDocTypeEntities<'a> {
+    /// Finds the end of the declaration starting at `from`, i.e. the first
+    /// top-level `>`, skipping over any quoted `EntityValue`/`SystemLiteral`/
+    /// `PubidLiteral` along the way. Those are allowed to contain a literal
+    /// `>`, so a plain `memchr(b'>', ...)` would stop short and leave `pos`
+    /// sitting inside a still-open quote.
+    fn skip_declaration(&self, from: usize) -> usize {
+        let mut pos = from;
+        while pos < self.content.len() {
+            match self.content[pos] {
+                b'>' => return pos + 1,
+                quote @ b'\'' | quote @ b'"' => {
+                    pos = match memchr::memchr(quote, &self.content[pos + 1..]) {
+                        Some(i) => pos + 1 + i + 1,
+                        None => return self.content.len(),
+                    };
+                }
+                _ => pos += 1,
+            }
+        }
+        self.content.len()
+    }
+}
+
+impl<'a> Iterator for BytesDocTypeEntities<'a> {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.content.len() {
+            let start = match find_subslice(&self.content[self.pos..], b"<!ENTITY") {
+                Some(i) => i,
+                None => {
+                    self.pos = self.content.len();
+                    return None;
+                }
+            };
+            let mut p = self.pos + start + b"<!ENTITY".len();
+
+            while p < self.content.len() && is_xml_whitespace(self.content[p]) {
+                p += 1;
+            }
+
+            // parameter entity (`<!ENTITY % name "value">`): not expandable
+            // as a general entity reference, skip the whole declaration
+            if self.content.get(p) == Some(&b'%') {
+                self.pos = self.skip_declaration(p);
+                continue;
+            }
+
+            let name_start = p;
+            while p < self.content.len() && !is_xml_whitespace(self.content[p]) {
+                p += 1;
+            }
+            let name = &self.content[name_start..p];
+
+            while p < self.content.len() && is_xml_whitespace(self.content[p]) {
+                p += 1;
+            }
+
+            let quote = match self.content.get(p) {
+                Some(&b'\'') | Some(&b'"') => self.content[p],
+                // external (SYSTEM/PUBLIC) or unparsed (NDATA) entity: skip
+                _ => {
+                    self.pos = self.skip_declaration(p);
+                    continue;
+                }
+            };
+            p += 1;
+
+            let value_start = p;
+            let value_end = match memchr::memchr(quote, &self.content[p..]) {
+                Some(i) => p + i,
+                None => {
+                    self.pos = self.content.len();
+                    return None;
+                }
+            };
+            let value = &self.content[value_start..value_end];
+
+            self.pos = self.skip_declaration(value_end + 1);
+            return Some((name.to_vec(), value.to_vec()));
+        }
+        None
     }
 }
 
@@ -295,6 +596,66 @@ impl<'a> BytesEnd<'a> {
     }
 }
 
+/// A struct to manage `Event::CData` events
+///
+/// By definition, the bytes between `<![CDATA[` and `]]>` are raw character
+/// data and were never escaped, so unlike `BytesText` this struct does not
+/// (and cannot correctly) provide an `unescaped()` method.
+#[derive(Clone, Debug)]
+pub struct BytesCData<'a> {
+    content: Cow<'a, [u8]>,
+}
+
+impl<'a> BytesCData<'a> {
+    /// Creates a new `BytesCData` borrowing a slice
+    #[inline]
+    pub fn borrowed(content: &'a [u8]) -> BytesCData<'a> {
+        BytesCData {
+            content: Cow::Borrowed(content),
+        }
+    }
+
+    /// Creates a new `BytesCData` owning its content
+    #[inline]
+    pub fn owned(content: Vec<u8>) -> BytesCData<'static> {
+        BytesCData {
+            content: Cow::Owned(content),
+        }
+    }
+
+    /// Converts the event into an owned event
+    pub fn into_owned(self) -> BytesCData<'static> {
+        BytesCData {
+            content: Cow::Owned(self.content.into_owned()),
+        }
+    }
+
+    /// Gets content of this CData event, raw (i.e. not unescaped)
+    ///
+    /// Unlike `BytesText::unescaped`, this never resolves entities, since
+    /// CDATA content is defined to be raw, unescaped character data.
+    pub fn content(&self) -> &[u8] {
+        &*self.content
+    }
+
+    /// Converts this CDATA event into an escaped `BytesText` event carrying
+    /// equivalent content, suitable for use as regular element content.
+    ///
+    /// The content is XML-escaped (`&`, `<` and `>` are all replaced), which
+    /// has the side effect of splitting any literal `]]>` sequence the CDATA
+    /// payload might have contained, since the trailing `>` becomes `&gt;`.
+    pub fn escape(self) -> BytesText<'a> {
+        match escape(&self.content) {
+            Cow::Borrowed(_) => BytesText {
+                content: self.content,
+            },
+            Cow::Owned(escaped) => BytesText {
+                content: Cow::Owned(escaped),
+            },
+        }
+    }
+}
+
 /// A struct to manage `Event::End` events
 #[derive(Clone, Debug)]
 pub struct BytesText<'a> {
@@ -335,6 +696,19 @@ impl<'a> BytesText<'a> {
         unescape(self).map_err(Error::EscapeError)
     }
 
+    /// gets unescaped content, also resolving custom entities
+    ///
+    /// Like `unescaped()`, but entity references that are not one of the five
+    /// predefined entities or a numeric character reference are looked up in
+    /// `custom_entities` (e.g. entities collected from a document's DTD via
+    /// `BytesDocType::entities_map`) instead of producing an error.
+    pub fn unescaped_with_custom_entities(
+        &self,
+        custom_entities: &HashMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<Cow<[u8]>> {
+        unescape_with_custom_entities(self, custom_entities)
+    }
+
     /// helper method to unescape then decode self using the reader encoding
     ///
     /// for performance reasons (could avoid allocating a `String`),
@@ -345,6 +719,17 @@ impl<'a> BytesText<'a> {
         self.unescaped().map(|e| reader.decode(&*e).into_owned())
     }
 
+    /// helper method to unescape then decode self using the reader encoding,
+    /// additionally resolving custom entities
+    pub fn unescape_and_decode_with_custom_entities<B: BufRead>(
+        &self,
+        reader: &Reader<B>,
+        custom_entities: &HashMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<String> {
+        self.unescaped_with_custom_entities(custom_entities)
+            .map(|e| reader.decode(&*e).into_owned())
+    }
+
     /// Gets escaped content
     ///
     /// Searches for any of `<, >, &, ', "` and xml escapes them.
@@ -353,6 +738,66 @@ impl<'a> BytesText<'a> {
     }
 }
 
+/// A struct to manage `Event::PI` events
+///
+/// A processing instruction's target (e.g. `xml-stylesheet`) is otherwise
+/// only recoverable by re-splitting the raw content on its first whitespace
+/// byte every time it's needed; `target_len` records that split point once,
+/// so `target()` and `content()`/`data()` can slice it out directly.
+#[derive(Clone, Debug)]
+pub struct BytesPI<'a> {
+    content: Cow<'a, [u8]>,
+    target_len: usize,
+}
+
+impl<'a> BytesPI<'a> {
+    /// Creates a new `BytesPI` from the given content (target followed by its data)
+    #[inline]
+    pub fn borrowed(content: &'a [u8], target_len: usize) -> BytesPI<'a> {
+        BytesPI {
+            content: Cow::Borrowed(content),
+            target_len: target_len,
+        }
+    }
+
+    /// Creates a new `BytesPI` from the given content (target followed by its data). Owns its content
+    #[inline]
+    pub fn owned(content: Vec<u8>, target_len: usize) -> BytesPI<'static> {
+        BytesPI {
+            content: Cow::Owned(content),
+            target_len: target_len,
+        }
+    }
+
+    /// Converts the event into an owned event
+    pub fn into_owned(self) -> BytesPI<'static> {
+        BytesPI {
+            content: Cow::Owned(self.content.into_owned()),
+            target_len: self.target_len,
+        }
+    }
+
+    /// Gets the PI target as `&[u8]` (the part before the first whitespace)
+    pub fn target(&self) -> &[u8] {
+        &self.content[..self.target_len]
+    }
+
+    /// Gets the PI content/data as `&[u8]` (everything after the target and
+    /// any whitespace separating it from the data)
+    pub fn content(&self) -> &[u8] {
+        let rest = &self.content[self.target_len..];
+        match rest.iter().position(|b| !is_xml_whitespace(*b)) {
+            Some(i) => &rest[i..],
+            None => &rest[rest.len()..],
+        }
+    }
+
+    /// Alias for `content()`
+    pub fn data(&self) -> &[u8] {
+        self.content()
+    }
+}
+
 /// Event to interprete node as they are parsed
 #[derive(Clone, Debug)]
 pub enum Event<'a> {
@@ -367,13 +812,13 @@ pub enum Event<'a> {
     /// Comment <!-- ... -->
     Comment(BytesText<'a>),
     /// CData <![CDATA[...]]>
-    CData(BytesText<'a>),
+    CData(BytesCData<'a>),
     /// Xml declaration <?xml ...?>
     Decl(BytesDecl<'a>),
     /// Processing instruction <?...?>
-    PI(BytesText<'a>),
+    PI(BytesPI<'a>),
     /// Doctype <!DOCTYPE...>
-    DocType(BytesText<'a>),
+    DocType(BytesDocType<'a>),
     /// Eof of file event
     Eof,
 }
@@ -392,6 +837,13 @@ impl<'a> Deref for BytesDecl<'a> {
     }
 }
 
+impl<'a> Deref for BytesDocType<'a> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &*self.content
+    }
+}
+
 impl<'a> Deref for BytesEnd<'a> {
     type Target = [u8];
     fn deref(&self) -> &[u8] {
@@ -406,6 +858,20 @@ impl<'a> Deref for BytesText<'a> {
     }
 }
 
+impl<'a> Deref for BytesPI<'a> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &*self.content
+    }
+}
+
+impl<'a> Deref for BytesCData<'a> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &*self.content
+    }
+}
+
 impl<'a> Deref for Event<'a> {
     type Target = [u8];
     fn deref(&self) -> &[u8] {
@@ -467,3 +933,149 @@ fn local_name() {
     assert_eq!(parsed_local_names[6], "bus:baz".to_string());
     assert_eq!(parsed_local_names[7], "bus:baz".to_string());
 }
+
+#[cfg(test)]
+#[test]
+fn unescape_with_custom_entities() {
+    let mut custom_entities = HashMap::new();
+    custom_entities.insert(b"foo".to_vec(), b"bar".to_vec());
+
+    // predefined and numeric references still resolve, and custom entities
+    // are substituted alongside them
+    let text = BytesText::borrowed(b"&amp;&#38;&foo;");
+    let unescaped = text
+        .unescaped_with_custom_entities(&custom_entities)
+        .expect("all entities should resolve");
+    assert_eq!(&*unescaped, b"&&bar".as_ref());
+
+    // no '&' in the input: stays borrowed rather than allocating
+    let text = BytesText::borrowed(b"plain text");
+    match text
+        .unescaped_with_custom_entities(&custom_entities)
+        .expect("plain text contains nothing to unescape")
+    {
+        Cow::Borrowed(_) => (),
+        Cow::Owned(_) => panic!("expected a borrowed Cow when there is nothing to unescape"),
+    }
+
+    // an entity that is neither predefined, numeric, nor in the custom map
+    // still errors, exactly like plain unescaped() would
+    let text = BytesText::borrowed(b"&unknown;");
+    assert!(text.unescaped_with_custom_entities(&custom_entities).is_err());
+
+    // a reference with no closing ';' is an error, not a panic
+    let text = BytesText::borrowed(b"&foo");
+    assert!(text.unescaped_with_custom_entities(&custom_entities).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn doctype_entities_map() {
+    let doctype = BytesDocType::from_text(BytesText::borrowed(
+        br#"root [
+            <!ENTITY % param "ignored">
+            <!ENTITY foo "first">
+            <!ENTITY foo "second">
+            <!ENTITY ext SYSTEM "external.dtd">
+            <!ENTITY bar "baz">
+        ]"#,
+    ));
+
+    let entities = doctype.entities_map();
+    assert_eq!(entities.len(), 2);
+    // first declaration wins on a duplicate name
+    assert_eq!(entities.get(b"foo".as_ref()).map(|v| &v[..]), Some(&b"first"[..]));
+    assert_eq!(entities.get(b"bar".as_ref()).map(|v| &v[..]), Some(&b"baz"[..]));
+    // parameter and external entities are not collected
+    assert!(entities.get(b"param".as_ref()).is_none());
+    assert!(entities.get(b"ext".as_ref()).is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn doctype_skips_literal_gt_inside_quoted_values() {
+    // a literal '>' inside a skipped parameter entity's value (legal XML:
+    // EntityValue doesn't exclude '>') must not make the scan stop short
+    // and later mistake text inside it for a real declaration
+    let doctype = BytesDocType::from_text(BytesText::borrowed(
+        br#"root [
+            <!ENTITY % p "x > <!ENTITY evil "1"> y">
+            <!ENTITY real "value">
+        ]"#,
+    ));
+
+    let entities = doctype.entities_map();
+    assert_eq!(entities.len(), 1);
+    assert_eq!(
+        entities.get(b"real".as_ref()).map(|v| &v[..]),
+        Some(&b"value"[..])
+    );
+    assert!(entities.get(b"evil".as_ref()).is_none());
+
+    // same hazard for an external entity's SystemLiteral
+    let doctype = BytesDocType::from_text(BytesText::borrowed(
+        br#"root [
+            <!ENTITY ext SYSTEM "a > b">
+            <!ENTITY real "value">
+        ]"#,
+    ));
+    let entities = doctype.entities_map();
+    assert_eq!(entities.len(), 1);
+    assert_eq!(
+        entities.get(b"real".as_ref()).map(|v| &v[..]),
+        Some(&b"value"[..])
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn pi_target_and_content() {
+    let pi = BytesPI::borrowed(b"xml-stylesheet href=\"style.css\"", 14);
+    assert_eq!(pi.target(), b"xml-stylesheet".as_ref());
+    assert_eq!(pi.content(), b"href=\"style.css\"".as_ref());
+    assert_eq!(pi.data(), pi.content());
+
+    // a PI with no data still has a target and an empty content
+    let pi = BytesPI::borrowed(b"xml-stylesheet", 14);
+    assert_eq!(pi.target(), b"xml-stylesheet".as_ref());
+    assert_eq!(pi.content(), b"".as_ref());
+}
+
+#[cfg(test)]
+#[test]
+fn cdata_is_not_unescaped() {
+    // raw '&' and '<' in CDATA content must survive untouched
+    let cdata = BytesCData::borrowed(b"1 < 2 && 3 > 2");
+    assert_eq!(cdata.content(), b"1 < 2 && 3 > 2".as_ref());
+
+    // escaping for round-tripping as element content also splits any
+    // literal ']]>' the CDATA payload contained, since '>' gets escaped
+    let cdata = BytesCData::borrowed(b"a]]>b");
+    let text = cdata.escape();
+    assert!(!text.escaped().windows(3).any(|w| w == b"]]>"));
+}
+
+#[cfg(test)]
+#[test]
+fn decl_normalized_accessors() {
+    let decl = BytesDecl::new(b"1.0", Some(b"UTF-8"), Some(b"yes"));
+    assert_eq!(&*decl.version_normalized().unwrap(), b"1.0".as_ref());
+    assert_eq!(
+        &*decl.standalone_normalized().unwrap().unwrap(),
+        b"yes".as_ref()
+    );
+    assert_eq!(decl.encoder(), ::encoding_rs::UTF_8);
+
+    // missing encoding falls back to UTF-8 rather than None
+    let decl = BytesDecl::new(b"1.0", None, None);
+    assert_eq!(decl.encoder(), ::encoding_rs::UTF_8);
+    assert!(decl.standalone_normalized().is_none());
+
+    // an out-of-spec version is rejected rather than silently accepted
+    let decl = BytesDecl::new(b"2.0", None, None);
+    assert!(decl.version_normalized().is_err());
+
+    // an out-of-spec standalone value is rejected
+    let decl = BytesDecl::new(b"1.0", None, Some(b"maybe"));
+    assert!(decl.standalone_normalized().unwrap().is_err());
+}